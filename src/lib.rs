@@ -0,0 +1,100 @@
+#[macro_use]
+extern crate derive_new;
+
+#[macro_use]
+extern crate error_chain;
+extern crate hex;
+extern crate hmac;
+extern crate reqwest;
+extern crate serde;
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate sha2;
+extern crate url;
+
+use reqwest::Client;
+use std::io::Read;
+use url::Url;
+
+pub mod auth;
+pub mod transport;
+
+pub mod errors {
+    error_chain! {
+        errors {}
+    }
+}
+
+use errors::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, new)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecReq {
+    pub id: String,
+    pub cmd_id_re: String,
+    pub cmd: String,
+}
+
+/// The subset of `MainConfig` needed to build and send a single `ExecReq`,
+/// so the core send logic can be exercised directly in tests without going
+/// through CLI parsing.
+#[derive(Clone, Debug)]
+pub struct RepeaterConfig {
+    pub name: String,
+    pub regex_pattern: String,
+    pub cmd: String,
+    pub dst_url: Url,
+}
+
+/// The testable core of the repeater: builds an `ExecReq` from its config
+/// and posts it to `dst_url`, independent of the CLI's retry, fan-out and
+/// scheduling layers.
+pub struct Repeater {
+    config: RepeaterConfig,
+}
+
+impl Repeater {
+    pub fn new(config: RepeaterConfig) -> Self {
+        Repeater { config }
+    }
+
+    pub fn build_request(&self) -> ExecReq {
+        ExecReq::new(self.config.name.clone(), self.config.regex_pattern.clone(), self.config.cmd.clone())
+    }
+
+    /// Builds and posts the `ExecReq` to `dst_url`, returning a success
+    /// message that includes the response body.
+    pub fn send_once(&self, client: &Client) -> Result<String> {
+        let req = self.build_request();
+
+        let res = client.post(self.config.dst_url.clone())
+            .json(&req)
+            .send();
+
+        match res {
+            Ok(mut resp) => {
+                if resp.status().is_success() {
+                    let mut content = String::new();
+                    let _ = resp.read_to_string(&mut content);
+
+                    Ok(format!("Success in sending command, body: {} ", content))
+                } else {
+                    bail!("Success in sending command, but returned status code: {:?}", resp.status());
+                }
+            },
+
+            Err(e) => {
+                bail!("Failed to send command: {}", e);
+            },
+        }
+    }
+
+    /// Serializes the `ExecReq` that would be sent, without performing any
+    /// network I/O. Backs the CLI's `--dry-run` flag.
+    pub fn dry_run(&self) -> Result<String> {
+        serde_json::to_string(&self.build_request())
+            .chain_err(|| "Unable to serialize dry-run request")
+    }
+}
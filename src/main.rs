@@ -14,36 +14,85 @@ extern crate structopt;
 
 #[macro_use]
 extern crate structopt_derive;
+extern crate comm_service_rep;
+#[cfg(not(unix))]
+extern crate ctrlc;
+#[cfg(unix)]
+extern crate libc;
+extern crate rand;
 extern crate url;
 
+use comm_service_rep::{auth, transport, ExecReq, Repeater, RepeaterConfig};
+use comm_service_rep::auth::AuthEnvelope;
+use comm_service_rep::errors::*;
+use comm_service_rep::transport::FramedConnection;
+use rand::Rng;
 use regex::Regex;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
 use std::io::{self, Read, Write};
 use std::iter;
-use std::process;
+use std::process::{self, Command, Stdio};
 use std::thread;
 use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use structopt::StructOpt;
 use url::Url;
 
+/// Job descriptor returned by the server for `--worker` mode.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RequestedJob {
+    job_id: String,
+    command: String,
+    args: Vec<String>,
+    env: Option<HashMap<String, String>>,
+}
+
+/// One incremental chunk of a running job's stdout/stderr.
 #[derive(Serialize, Deserialize, Clone, Debug, new)]
 #[serde(rename_all = "camelCase")]
-struct ExecReq {
-    id: String,
-    cmd_id_re: String,
-    cmd: String,
+struct JobOutputChunk {
+    job_id: String,
+    stream: String,
+    seq: u64,
+    data: String,
 }
 
-mod errors {
-    error_chain! {
-        errors {}
-    }
+/// Final exit status reported once a job's process has terminated.
+#[derive(Serialize, Deserialize, Clone, Debug, new)]
+#[serde(rename_all = "camelCase")]
+struct JobExitStatus {
+    job_id: String,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+}
+
+/// How a tick's send is distributed across multiple `-d` destinations.
+/// Parsed via `FromStr` (like `Regex`/`Url` above) so an invalid
+/// `--fanout` value fails CLI parsing instead of silently falling back to
+/// broadcast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FanoutMode {
+    Broadcast,
+    RoundRobin,
 }
 
-use errors::*;
+impl std::str::FromStr for FanoutMode {
+    type Err = String;
 
-#[derive(StructOpt, Debug)]
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        match s {
+            "broadcast" => Ok(FanoutMode::Broadcast),
+            "round-robin" => Ok(FanoutMode::RoundRobin),
+            other => Err(format!("Invalid fanout mode '{}': expected \"broadcast\" or \"round-robin\"", other)),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug, Clone)]
 #[structopt(name = "Comm Service Calling Repeater", about = "Program to repeatedly send command to the given address.")]
 struct MainConfig {
     #[structopt(short = "n", default_value = "caller", help = "Name of the caller")]
@@ -55,23 +104,718 @@ struct MainConfig {
     #[structopt(short = "c", help = "Command to run")]
     cmd: String,
 
-    #[structopt(short = "d", help = "Server to send command to")]
-    dst_url: Url,
+    #[structopt(short = "d", required = true, help = "Server(s) to send command to; repeat -d to configure more than one destination")]
+    dst_urls: Vec<Url>,
+
+    #[structopt(long = "fanout", default_value = "broadcast", help = "How to distribute each tick across multiple -d destinations: \"broadcast\" (send to all) or \"round-robin\" (rotate through them)")]
+    fanout: FanoutMode,
 
     #[structopt(short = "i", default_value = "1000", help = "Send to interval in milliseconds")]
     interval: u32,
+
+    #[structopt(long = "worker", help = "Run as a job worker: poll dst_url for a job descriptor, execute it, and stream output back instead of sending the fixed command")]
+    worker: bool,
+
+    #[structopt(long = "max-retries", default_value = "5", help = "Maximum number of retries for a failed send before it is written to the dead-letter log")]
+    max_retries: u32,
+
+    #[structopt(long = "base-delay-ms", default_value = "100", help = "Base delay in milliseconds for exponential backoff between retries")]
+    base_delay_ms: u64,
+
+    #[structopt(long = "max-delay-ms", default_value = "30000", help = "Maximum delay in milliseconds for exponential backoff between retries")]
+    max_delay_ms: u64,
+
+    #[structopt(long = "dead-letter-log", default_value = "dead_letter.log", help = "File to append failed requests to once retries are exhausted")]
+    dead_letter_log: String,
+
+    #[structopt(long = "persistent", help = "Open a single length-prefixed framed connection to dst_url and reuse it for every tick instead of a fresh HTTP request each time")]
+    persistent: bool,
+
+    #[structopt(long = "auth-key", help = "HMAC-SHA256 key used to sign each request; when set, requests are wrapped in a time-bounded, signed envelope")]
+    auth_key: Option<String>,
+
+    #[structopt(long = "auth-key-id", default_value = "default", help = "Identifier for the auth key, sent alongside the signature so the server knows which key to verify against")]
+    auth_key_id: String,
+
+    #[structopt(long = "auth-ttl-ms", default_value = "5000", help = "How far in the future expires_at is set for each signed request, in milliseconds")]
+    auth_ttl_ms: u64,
+
+    #[structopt(long = "dry-run", help = "Serialize and print the request that would be sent on each tick, without sending it over the network")]
+    dry_run: bool,
+}
+
+/// The signing material needed to attach an `Authorization` header to a
+/// request, derived from `--auth-key` once at startup.
+struct AuthConfig {
+    key: Vec<u8>,
+    key_id: String,
+    ttl_ms: u64,
+}
+
+impl AuthConfig {
+    fn from_config(config: &MainConfig) -> Option<AuthConfig> {
+        config.auth_key.as_ref().map(|key| AuthConfig {
+            key: key.clone().into_bytes(),
+            key_id: config.auth_key_id.clone(),
+            ttl_ms: config.auth_ttl_ms,
+        })
+    }
+}
+
+impl MainConfig {
+    /// The destination `--worker` and `--persistent` mode address, since
+    /// those modes hold a single connection rather than fanning out.
+    fn primary_dst_url(&self) -> &Url {
+        &self.dst_urls[0]
+    }
+}
+
+/// Polls `dst_url` for a `RequestedJob` using `poll_client` (bounded by a
+/// request timeout so a stalled long-poll can't hang forever), then runs
+/// it to completion and streams its output back via `client`. Returns
+/// `Ok(true)` if a job was found and run, or `Ok(false)` if the server had
+/// no work available (empty body / 204).
+fn poll_and_run_job(poll_client: &Client, client: &Client, dst_url: &Url) -> Result<bool> {
+    let mut resp = poll_client.get(dst_url.clone())
+        .send()
+        .chain_err(|| "Failed to poll for job")?;
+
+    if resp.status() == StatusCode::NoContent {
+        return Ok(false);
+    }
+
+    if !resp.status().is_success() {
+        bail!("Polling for job returned status code: {:?}", resp.status());
+    }
+
+    let mut body = String::new();
+    resp.read_to_string(&mut body)
+        .chain_err(|| "Unable to read job poll response body")?;
+
+    if body.trim().is_empty() {
+        return Ok(false);
+    }
+
+    let job: RequestedJob = serde_json::from_str(&body)
+        .chain_err(|| "Unable to deserialize RequestedJob")?;
+
+    run_job(client, dst_url, job)?;
+
+    Ok(true)
+}
+
+/// Runs a single job's command, posting incremental output chunks and a
+/// final exit-status record back to `dst_url`.
+fn run_job(client: &Client, dst_url: &Url, job: RequestedJob) -> Result<()> {
+    let job_id = job.job_id.clone();
+    println!("Running job {}: {} {:?}", job_id, job.command, job.args);
+
+    let mut cmd = Command::new(&job.command);
+    cmd.args(&job.args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(ref env) = job.env {
+        for (k, v) in env {
+            cmd.env(k, v);
+        }
+    }
+
+    let mut child = cmd.spawn()
+        .chain_err(|| format!("Unable to spawn job {}", job_id))?;
+
+    let seq = Arc::new(Mutex::new(0u64));
+
+    let stdout_handle = child.stdout.take().map(|stdout| {
+        let job_id = job_id.clone();
+        let client = client.clone();
+        let dst_url = dst_url.clone();
+        let seq = seq.clone();
+
+        thread::spawn(move || stream_output(&client, &dst_url, &job_id, "stdout", stdout, &seq))
+    });
+
+    let stderr_handle = child.stderr.take().map(|stderr| {
+        let job_id = job_id.clone();
+        let client = client.clone();
+        let dst_url = dst_url.clone();
+        let seq = seq.clone();
+
+        thread::spawn(move || stream_output(&client, &dst_url, &job_id, "stderr", stderr, &seq))
+    });
+
+    if let Some(handle) = stdout_handle {
+        let _ = handle.join();
+    }
+
+    if let Some(handle) = stderr_handle {
+        let _ = handle.join();
+    }
+
+    let status = child.wait()
+        .chain_err(|| format!("Unable to wait for job {}", job_id))?;
+
+    let exit_status = JobExitStatus::new(job_id.clone(), status.code(), unix_signal(&status));
+
+    let res = client.post(dst_url.clone())
+        .json(&exit_status)
+        .send();
+
+    match res {
+        Ok(ref resp) if resp.status().is_success() => {
+            println!("Reported exit status for job {}", job_id);
+        },
+
+        Ok(resp) => {
+            println!("Reporting exit status for job {} returned status code: {:?}", job_id, resp.status());
+        },
+
+        Err(e) => {
+            println!("Failed to report exit status for job {}: {}", job_id, e);
+        },
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn unix_signal(status: &process::ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn unix_signal(_status: &process::ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Decodes as much of `bytes` as is safe to decode right now: any fully
+/// valid UTF-8 is lossily decoded (replacing genuinely invalid byte
+/// sequences with `U+FFFD` rather than dropping data), while a
+/// possibly-incomplete multi-byte sequence at the very end is left
+/// undecoded so the next read can complete it instead of corrupting a
+/// character that happened to straddle a 4096-byte read boundary. Returns
+/// the decoded string and how many bytes of `bytes` it consumed.
+fn decode_utf8_prefix(bytes: &[u8]) -> (String, usize) {
+    let mut decoded = String::new();
+    let mut consumed = 0;
+
+    loop {
+        match std::str::from_utf8(&bytes[consumed..]) {
+            Ok(valid) => {
+                decoded.push_str(valid);
+                consumed = bytes.len();
+                break;
+            },
+
+            Err(e) => {
+                let valid_up_to = e.valid_up_to();
+                decoded.push_str(std::str::from_utf8(&bytes[consumed..consumed + valid_up_to]).expect("already validated"));
+                consumed += valid_up_to;
+
+                match e.error_len() {
+                    // A genuinely invalid byte sequence, not just a
+                    // truncated one: replace it and keep scanning.
+                    Some(invalid_len) => {
+                        decoded.push('\u{fffd}');
+                        consumed += invalid_len;
+                    },
+
+                    // The remaining bytes are a possibly-incomplete
+                    // sequence; leave them for the next read to complete.
+                    None => break,
+                }
+            },
+        }
+    }
+
+    (decoded, consumed)
+}
+
+/// Posts one sequenced `JobOutputChunk` of `data` back to `dst_url`.
+fn post_output_chunk(client: &Client, dst_url: &Url, job_id: &str, stream: &str, seq: &Arc<Mutex<u64>>, data: String) {
+    let next_seq = {
+        let mut guard = match seq.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                println!("Unable to get seq lock for job {}: {}", job_id, e);
+                return;
+            },
+        };
+
+        let current = *guard;
+        *guard += 1;
+        current
+    };
+
+    let chunk = JobOutputChunk::new(job_id.to_string(), stream.to_string(), next_seq, data);
+
+    let res = client.post(dst_url.clone())
+        .json(&chunk)
+        .send();
+
+    if let Err(e) = res {
+        println!("Failed to send {} chunk for job {}: {}", stream, job_id, e);
+    }
+}
+
+/// Reads a child process stream in raw byte chunks, posting each chunk as a
+/// sequenced `JobOutputChunk` back to `dst_url`. Reading raw bytes rather
+/// than `BufRead::lines()` means a command that emits non-UTF-8 output
+/// (binary data, a stray invalid byte) doesn't abort the stream partway
+/// through. Bytes are buffered across reads so a multi-byte UTF-8
+/// character split across two 4096-byte reads is decoded correctly rather
+/// than corrupted into replacement characters; only genuinely invalid
+/// sequences are replaced.
+fn stream_output<R: Read>(client: &Client, dst_url: &Url, job_id: &str, stream: &str, mut reader: R, seq: &Arc<Mutex<u64>>) {
+    let mut buf = [0u8; 4096];
+    let mut pending: Vec<u8> = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => {
+                if !pending.is_empty() {
+                    let data = String::from_utf8_lossy(&pending).into_owned();
+                    post_output_chunk(client, dst_url, job_id, stream, seq, data);
+                }
+
+                break;
+            },
+
+            Ok(read) => read,
+
+            Err(e) => {
+                println!("Error reading {} for job {}: {}", stream, job_id, e);
+                break;
+            },
+        };
+
+        pending.extend_from_slice(&buf[..read]);
+
+        let (data, consumed) = decode_utf8_prefix(&pending);
+        pending.drain(..consumed);
+
+        if !data.is_empty() {
+            post_output_chunk(client, dst_url, job_id, stream, seq, data);
+        }
+    }
+}
+
+/// Computes the full-jitter backoff delay for a given retry `attempt`
+/// (0-indexed): `sleep` is chosen uniformly from `[0, cap]` where
+/// `cap = min(max_delay_ms, base_delay_ms * 2^attempt)`. Full jitter avoids
+/// a thundering herd of retries when many repeaters target the same server.
+fn full_jitter_delay(base_delay_ms: u64, max_delay_ms: u64, attempt: u32) -> Duration {
+    let backoff = base_delay_ms.saturating_mul(1u64.checked_shl(attempt).unwrap_or(u64::max_value()));
+    let cap = backoff.min(max_delay_ms);
+
+    // `gen_range(0, cap + 1)` would overflow/panic on an empty range when
+    // `cap` is `u64::MAX` (e.g. a pathological `--max-delay-ms`), so treat
+    // that as the unbounded case and sample the full u64 range directly.
+    let jittered = if cap == u64::max_value() {
+        rand::thread_rng().gen()
+    } else {
+        rand::thread_rng().gen_range(0, cap + 1)
+    };
+
+    Duration::from_millis(jittered)
+}
+
+/// Appends a failed `ExecReq` as a single serialized JSON line to the
+/// dead-letter log once retries have been exhausted.
+fn append_dead_letter(path: &str, req: &ExecReq) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .chain_err(|| format!("Unable to open dead-letter log: {}", path))?;
+
+    let line = serde_json::to_string(req)
+        .chain_err(|| "Unable to serialize dead-letter request")?;
+
+    writeln!(file, "{}", line)
+        .chain_err(|| format!("Unable to write to dead-letter log: {}", path))?;
+
+    Ok(())
+}
+
+/// Posts `req` to `dst_url`, retrying up to `max_retries` times with
+/// exponential backoff and full jitter on failure. If every attempt fails,
+/// the request is appended to `dead_letter_log` instead of being dropped.
+/// When `auth` is set, `req` is wrapped in a freshly time-stamped
+/// `AuthEnvelope` and signed on every attempt, so a request that only
+/// succeeds after several retries still carries an up-to-date validity
+/// window.
+fn send_with_retries(
+    client: &Client,
+    dst_url: Url,
+    req: ExecReq,
+    max_retries: u32,
+    base_delay_ms: u64,
+    max_delay_ms: u64,
+    dead_letter_log: &str,
+    auth: Option<&AuthConfig>,
+) -> Result<String> {
+    let mut attempt = 0;
+
+    loop {
+        let outcome = match auth {
+            Some(auth) => {
+                let envelope = AuthEnvelope::wrap(req.clone(), auth.ttl_ms)?;
+                let header = auth::sign(&auth.key, &auth.key_id, &envelope)?;
+
+                let res = client.post(dst_url.clone())
+                    .header("Authorization", header)
+                    .json(&envelope)
+                    .send();
+
+                match res {
+                    Ok(mut resp) => {
+                        if resp.status().is_success() {
+                            let mut content = String::new();
+                            let _ = resp.read_to_string(&mut content);
+
+                            Ok(format!("Success in sending command, body: {} ", content))
+                        } else {
+                            Err(format!("returned status code: {:?}", resp.status()))
+                        }
+                    },
+
+                    Err(e) => Err(format!("{}", e)),
+                }
+            },
+
+            None => {
+                let repeater = Repeater::new(RepeaterConfig {
+                    name: req.id.clone(),
+                    regex_pattern: req.cmd_id_re.clone(),
+                    cmd: req.cmd.clone(),
+                    dst_url: dst_url.clone(),
+                });
+
+                repeater.send_once(client).map_err(|e| format!("{}", e))
+            },
+        };
+
+        match outcome {
+            Ok(msg) => return Ok(msg),
+
+            Err(reason) => {
+                if attempt >= max_retries {
+                    append_dead_letter(dead_letter_log, &req)
+                        .chain_err(|| "Unable to record failed request to dead-letter log")?;
+
+                    bail!("Failed to send command after {} retries: {}", max_retries, reason);
+                }
+
+                println!("Send attempt {} failed ({}), retrying...", attempt + 1, reason);
+                thread::sleep(full_jitter_delay(base_delay_ms, max_delay_ms, attempt));
+                attempt += 1;
+            },
+        }
+    }
+}
+
+/// Runs the repeater in `--persistent` mode: opens a single length-prefixed
+/// framed connection to `dst_url` and reuses it for every tick instead of
+/// paying for a fresh TCP/HTTP handshake each `interval`.
+fn run_persistent(config: &MainConfig, sync_pair: &Arc<(Mutex<bool>, Condvar)>) -> Result<()> {
+    let interval = Duration::from_millis(config.interval as u64);
+
+    let host = config.primary_dst_url().host_str()
+        .ok_or_else(|| Error::from("dst_url has no host"))?;
+
+    let port = config.primary_dst_url().port_or_known_default()
+        .ok_or_else(|| Error::from("dst_url has no resolvable port"))?;
+
+    let mut conn = FramedConnection::connect((host, port))
+        .chain_err(|| "Unable to establish persistent framed connection")?;
+
+    // Bound each reply wait by the tick interval so a stalled or
+    // non-replying server can't wedge `recv` forever and block graceful
+    // shutdown, which only gets checked at the top of this loop.
+    conn.set_read_timeout(Some(interval))
+        .chain_err(|| "Unable to set persistent connection read timeout")?;
+
+    loop {
+        let &(ref m, ref cv) = &**sync_pair;
+
+        let is_interrupted = {
+            let guard = m.lock()
+                .map_err(|e| format!("Unable to get mutex lock in persistent loop: {}", e))?;
+
+            let (guard, _) = cv.wait_timeout(guard, interval)
+                .map_err(|e| format!("Unable to wait for condvar timeout: {}", e))?;
+
+            *guard
+        };
+
+        if is_interrupted {
+            break;
+        }
+
+        let req = ExecReq::new(config.name.clone(), config.regex_pattern.to_string(), config.cmd.clone());
+
+        let seq = conn.send(&req)
+            .chain_err(|| "Unable to send framed request")?;
+
+        match conn.recv::<transport::Framed<serde_json::Value>>() {
+            Ok(reply) => println!("Framed reply for seq {} (sent as {}): {:?}", reply.seq, seq, reply.body),
+
+            // Includes the read timeout case: no reply within this tick's
+            // window isn't fatal, it just means we loop back around,
+            // re-check the shutdown flag, and try again next tick.
+            Err(e) => println!("Unable to read framed reply for seq {} (will retry next tick): {}", seq, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the repeater in `--worker` mode: repeatedly polls `dst_url` for a
+/// job, executing at most one job at a time, and sleeps for `interval`
+/// whenever the server has no work.
+fn run_worker(config: &MainConfig, sync_pair: &Arc<(Mutex<bool>, Condvar)>) -> Result<()> {
+    let interval = Duration::from_millis(config.interval as u64);
+
+    // Bounded by `interval` so a stalled long-poll can't wedge shutdown the
+    // way persistent mode's `recv` used to before it got a read timeout.
+    let poll_client = Client::builder()
+        .timeout(interval)
+        .build()
+        .chain_err(|| "Error creating HTTP poll client")?;
+
+    // No timeout here: once a job is found, running it and streaming its
+    // output back may legitimately take far longer than one poll interval.
+    let client = Client::new()
+        .chain_err(|| "Error creating HTTP client")?;
+
+    loop {
+        let &(ref m, ref cv) = &**sync_pair;
+
+        let is_interrupted = {
+            let guard = m.lock()
+                .map_err(|e| format!("Unable to get mutex lock in worker loop: {}", e))?;
+
+            let (guard, _) = cv.wait_timeout(guard, Duration::from_millis(0))
+                .map_err(|e| format!("Unable to wait for condvar timeout: {}", e))?;
+
+            *guard
+        };
+
+        if is_interrupted {
+            break;
+        }
+
+        match poll_and_run_job(&poll_client, &client, config.primary_dst_url()) {
+            // a job ran to completion; poll again immediately for more work
+            Ok(true) => {},
+
+            Ok(false) => {
+                thread::sleep(interval);
+            },
+
+            Err(e) => {
+                println!("Worker poll error: {}", e);
+                thread::sleep(interval);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Flips the shared interrupt flag and wakes anyone waiting on the condvar.
+/// Called on SIGINT/SIGTERM (from the signal poller thread on Unix, from the
+/// `ctrlc` handler elsewhere) as well as from the ENTER-key watcher, so
+/// either trigger shuts the repeater down the same way.
+fn trigger_shutdown(sync_pair: &Arc<(Mutex<bool>, Condvar)>, source: &str) {
+    println!("Terminating (triggered by {})...", source);
+    let &(ref m, ref cv) = &**sync_pair;
+
+    match m.lock() {
+        Ok(mut guard) => {
+            *guard = true;
+        },
+
+        Err(e) => {
+            println!("Unable to get mutex lock to trigger shutdown: {}", e);
+            return;
+        },
+    }
+
+    cv.notify_all();
+}
+
+/// Set by `handle_unix_signal` when SIGINT or SIGTERM arrives. Only an
+/// atomic store is async-signal-safe to do from inside the handler itself;
+/// the `Mutex`/`Condvar` shutdown trigger is flipped from the poller thread
+/// `install_unix_signal_handlers` spawns instead.
+#[cfg(unix)]
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn handle_unix_signal(_signum: libc::c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Installs handlers for SIGINT and SIGTERM directly via libc, since the
+/// `ctrlc` crate only handles SIGTERM (and SIGHUP) when built with its
+/// `termination` feature enabled, and running cleanly under a process
+/// supervisor that stops services with SIGTERM is the whole point of
+/// `--worker`/`--persistent`. A background thread polls the flag the
+/// handler sets and converts it into a normal shutdown trigger.
+#[cfg(unix)]
+fn install_unix_signal_handlers(sync_pair: Arc<(Mutex<bool>, Condvar)>) -> Result<()> {
+    unsafe {
+        if libc::signal(libc::SIGINT, handle_unix_signal as libc::sighandler_t) == libc::SIG_ERR {
+            bail!("Unable to install SIGINT handler");
+        }
+
+        if libc::signal(libc::SIGTERM, handle_unix_signal as libc::sighandler_t) == libc::SIG_ERR {
+            bail!("Unable to install SIGTERM handler");
+        }
+    }
+
+    thread::spawn(move || {
+        loop {
+            if SHUTDOWN_REQUESTED.load(Ordering::SeqCst) {
+                trigger_shutdown(&sync_pair, "signal");
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(50));
+        }
+    });
+
+    Ok(())
+}
+
+/// Windows has no SIGTERM, so Ctrl-C via `ctrlc` is the only signal this
+/// platform needs to handle.
+#[cfg(not(unix))]
+fn install_unix_signal_handlers(sync_pair: Arc<(Mutex<bool>, Condvar)>) -> Result<()> {
+    ctrlc::set_handler(move || trigger_shutdown(&sync_pair, "signal"))
+        .chain_err(|| "Unable to install Ctrl-C handler")
+}
+
+/// Installs the two shutdown triggers: a SIGINT/SIGTERM handler for running
+/// cleanly under a process supervisor, and a background ENTER-key watcher
+/// kept for interactive use.
+fn install_shutdown_triggers(sync_pair: Arc<(Mutex<bool>, Condvar)>) -> Result<()> {
+    let sync_pair_signal = sync_pair.clone();
+
+    install_unix_signal_handlers(sync_pair_signal)?;
+
+    thread::spawn(move || {
+        println!("Press [ENTER] to terminate...");
+        let mut buf = String::new();
+
+        if io::stdin().read_line(&mut buf).is_ok() {
+            trigger_shutdown(&sync_pair, "stdin");
+        }
+    });
+
+    Ok(())
+}
+
+/// Blocks the calling thread until the shared interrupt flag is set by
+/// whichever shutdown trigger fires first.
+fn block_until_shutdown(sync_pair: &Arc<(Mutex<bool>, Condvar)>) -> Result<()> {
+    let &(ref m, ref cv) = &**sync_pair;
+
+    let guard = m.lock()
+        .map_err(|e| format!("Unable to get mutex lock in main thread: {}", e))?;
+
+    cv.wait_while(guard, |interrupted| !*interrupted)
+        .map_err(|e| format!("Unable to wait for shutdown: {}", e))?;
+
+    Ok(())
+}
+
+/// Spawns `mode_fn` on its own thread, waits until a shutdown trigger has
+/// set the shared interrupt flag, then joins the thread. Shared by the
+/// single-threaded background modes (`--worker`, `--persistent`) that run
+/// one long-lived loop rather than the per-tick spawn loop.
+fn run_background_mode<F>(label: &'static str, sync_pair: Arc<(Mutex<bool>, Condvar)>, mode_fn: F) -> Result<()>
+    where F: FnOnce(&Arc<(Mutex<bool>, Condvar)>) -> Result<()> + Send + 'static
+{
+    let sync_pair_child = sync_pair.clone();
+
+    let child = thread::spawn(move || {
+        if let Err(e) = mode_fn(&sync_pair_child) {
+            println!("{} thread error: {}", label, e);
+        }
+    });
+
+    install_shutdown_triggers(sync_pair.clone())?;
+    block_until_shutdown(&sync_pair)?;
+
+    println!("Waiting for {} thread to terminate...", label);
+
+    if let Err(e) = child.join() {
+        println!("Error joining {} thread: {:?}", label, e);
+    }
+
+    Ok(())
+}
+
+/// Checks for flag combinations that would otherwise silently do the wrong
+/// thing in `--worker`/`--persistent` mode: a hard error for `--auth-key`,
+/// since those transports don't sign requests and shipping them unsigned
+/// would make the flag a silent no-op, and a startup warning for the
+/// flags that are simply unused there so a daemon operator isn't surprised.
+fn validate_config(config: &MainConfig) -> Result<()> {
+    let background_mode = config.worker || config.persistent;
+
+    if config.auth_key.is_some() && background_mode {
+        bail!("--auth-key is not supported together with --worker or --persistent: \
+               requests sent over those transports are not signed, so the \
+               protection would silently be dropped");
+    }
+
+    if background_mode {
+        if config.dry_run {
+            println!("Warning: --dry-run has no effect in --worker/--persistent mode");
+        }
+
+        if config.fanout == FanoutMode::RoundRobin {
+            println!("Warning: --fanout is ignored in --worker/--persistent mode; only the first -d destination is used");
+        }
+
+        if config.dst_urls.len() > 1 {
+            println!("Warning: only the first -d destination ({}) is used in --worker/--persistent mode", config.primary_dst_url());
+        }
+    }
+
+    Ok(())
 }
 
 fn run() -> Result<()> {
     let config = MainConfig::from_args();
     println!("Config: {:?}", config);
 
+    validate_config(&config)?;
+
     let interval = Duration::from_millis(config.interval as u64);
     let sync_pair = Arc::new((Mutex::new(false), Condvar::new()));
     let sync_pair_child = sync_pair.clone();
 
+    if config.worker {
+        let config_child = config.clone();
+        return run_background_mode("worker", sync_pair, move |sync_pair| run_worker(&config_child, sync_pair));
+    }
+
+    if config.persistent {
+        let config_child = config.clone();
+        return run_background_mode("persistent", sync_pair, move |sync_pair| run_persistent(&config_child, sync_pair));
+    }
+
+    let auth_config = Arc::new(AuthConfig::from_config(&config));
+
     let child = thread::spawn(move || {
         let &(ref m, ref cv) = &*sync_pair_child;
+        let mut rr_index: usize = 0;
 
         iter::repeat(())
             .any(|_| {
@@ -95,47 +839,69 @@ fn run() -> Result<()> {
                 match match_fn() {
                     // not interrupted
                     Ok(false) => {
-                        // sends command here in a separate thread to preserve timing
-                        let dst_url = config.dst_url.clone();
-                        let name = config.name.clone();
-                        let regex_pattern = config.regex_pattern.to_string();
-                        let cmd = config.cmd.clone();
-
-                        // detach the HTTP client thread
-                        thread::spawn(move || {
-                            let client_fn = || -> Result<String> {
-                                let client = match Client::new() {
-                                    Ok(client) => client,
-                                    Err(e) => bail!("Error creating HTTP client: {}", e),
-                                };
-
-                                let res = client.post(dst_url)
-                                    .json(&ExecReq::new(name, regex_pattern, cmd))
-                                    .send();
-
-                                match res {
-                                    Ok(mut resp) => {
-                                        if resp.status().is_success() {
-                                            let mut content = String::new();
-                                            let _ = resp.read_to_string(&mut content);
-
-                                            Ok(format!("Success in sending command, body: {} ", content))
-                                        } else {
-                                            bail!("Success in sending command, but returned status code: {:?}", resp.status());
-                                        }
-                                    },
-
-                                    Err(e) => {
-                                        bail!("Failed to send command: {}", e);
-                                    },
+                        // pick this tick's destinations: broadcast to all, or rotate one at a time
+                        let targets: Vec<Url> = if config.fanout == FanoutMode::RoundRobin {
+                            let target = config.dst_urls[rr_index % config.dst_urls.len()].clone();
+                            rr_index = rr_index.wrapping_add(1);
+
+                            vec![target]
+                        } else {
+                            config.dst_urls.clone()
+                        };
+
+                        for dst_url in targets {
+                            if config.dry_run {
+                                let repeater = Repeater::new(RepeaterConfig {
+                                    name: config.name.clone(),
+                                    regex_pattern: config.regex_pattern.to_string(),
+                                    cmd: config.cmd.clone(),
+                                    dst_url: dst_url.clone(),
+                                });
+
+                                match repeater.dry_run() {
+                                    Ok(json) => println!("Dry run: {}", json),
+                                    Err(e) => println!("Dry run error: {}", e),
                                 }
-                            };
 
-                            match client_fn() {
-                                Ok(msg) => println!("{}", msg),
-                                Err(e) => println!("HTTP thread error: {}", e),
+                                continue;
                             }
-                        });
+
+                            // sends command here in a separate thread to preserve timing
+                            let name = config.name.clone();
+                            let regex_pattern = config.regex_pattern.to_string();
+                            let cmd = config.cmd.clone();
+                            let max_retries = config.max_retries;
+                            let base_delay_ms = config.base_delay_ms;
+                            let max_delay_ms = config.max_delay_ms;
+                            let dead_letter_log = config.dead_letter_log.clone();
+                            let auth_config = auth_config.clone();
+
+                            // detach the HTTP client thread
+                            thread::spawn(move || {
+                                let client_fn = || -> Result<String> {
+                                    let client = match Client::new() {
+                                        Ok(client) => client,
+                                        Err(e) => bail!("Error creating HTTP client: {}", e),
+                                    };
+
+                                    send_with_retries(
+                                        &client,
+                                        dst_url,
+                                        ExecReq::new(name, regex_pattern, cmd),
+                                        max_retries,
+                                        base_delay_ms,
+                                        max_delay_ms,
+                                        &dead_letter_log,
+                                        auth_config.as_ref().as_ref(),
+                                    )
+                                };
+
+                                match client_fn() {
+                                    Ok(msg) => println!("{}", msg),
+                                    Err(e) => println!("HTTP thread error: {}", e),
+                                }
+                            });
+                        }
 
                         false
                     },
@@ -151,29 +917,9 @@ fn run() -> Result<()> {
             });
     });
 
-    // main thread blocking until something is entered into buffer
-    println!("Press [ENTER] to terminate...");
-
-    let mut buf = String::new();
-
-    io::stdin().read_line(&mut buf)
-        .chain_err(|| "Unable to read into buffer")?;
-
-    println!("Terminating...");
-    let &(ref m, ref cv) = &*sync_pair;
-
-    {
-        // must scope to lock as little as possible
-        match m.lock() {
-            Ok(mut guard) => {
-                *guard = true;
-            },
-
-            Err(e) => bail!("Unable to get mutex lock in main thread: {}", e),
-        }
-    }
+    install_shutdown_triggers(sync_pair.clone())?;
+    block_until_shutdown(&sync_pair)?;
 
-    cv.notify_one();
     println!("Waiting for child thread to terminate...");
 
     if let Err(e) = child.join() {
@@ -0,0 +1,118 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use errors::*;
+
+const CONTENT_LENGTH_HEADER: &str = "Content-Length";
+
+/// A message framed with a monotonically increasing sequence id so a
+/// reply can be correlated with the request that caused it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Framed<T> {
+    pub seq: u64,
+    pub body: T,
+}
+
+/// A persistent connection that exchanges length-prefixed JSON frames in
+/// the style of the debug-adapter protocol: each frame is a
+/// `Content-Length: N\r\n\r\n` header followed by exactly `N` bytes of JSON
+/// body. Keeping one connection open avoids paying for a fresh handshake
+/// on every send.
+pub struct FramedConnection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    next_seq: u64,
+}
+
+impl FramedConnection {
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .chain_err(|| "Unable to connect framed transport")?;
+
+        let writer = stream.try_clone()
+            .chain_err(|| "Unable to clone framed transport stream")?;
+
+        Ok(FramedConnection {
+            reader: BufReader::new(stream),
+            writer,
+            next_seq: 0,
+        })
+    }
+
+    /// Sets (or clears) a timeout on the underlying socket reads, so `recv`
+    /// can't block indefinitely if the peer stalls or never replies. This
+    /// lets a caller loop back around and check for shutdown instead of
+    /// wedging on a single blocked read.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.reader.get_ref().set_read_timeout(timeout)
+            .chain_err(|| "Unable to set framed transport read timeout")
+    }
+
+    /// Serializes `body` as a length-prefixed JSON frame tagged with the
+    /// next sequence id, writes it to the stream, and returns that id.
+    pub fn send<T: Serialize>(&mut self, body: &T) -> Result<u64> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        let payload = serde_json::to_string(&Framed { seq, body })
+            .chain_err(|| "Unable to serialize framed message")?;
+
+        write!(self.writer, "{}: {}\r\n\r\n", CONTENT_LENGTH_HEADER, payload.len())
+            .chain_err(|| "Unable to write frame header")?;
+
+        self.writer.write_all(payload.as_bytes())
+            .chain_err(|| "Unable to write frame body")?;
+
+        self.writer.flush()
+            .chain_err(|| "Unable to flush frame")?;
+
+        Ok(seq)
+    }
+
+    /// Reads one length-prefixed JSON frame: header lines up to a blank
+    /// `\r\n\r\n`, then exactly `Content-Length` bytes of JSON body.
+    pub fn recv<T: DeserializeOwned>(&mut self) -> Result<Framed<T>> {
+        let mut content_length = None;
+
+        loop {
+            let mut line = String::new();
+
+            let read = self.reader.read_line(&mut line)
+                .chain_err(|| "Unable to read frame header line")?;
+
+            if read == 0 {
+                bail!("Connection closed while reading frame header");
+            }
+
+            let trimmed = line.trim_end_matches(|c| c == '\r' || c == '\n');
+
+            if trimmed.is_empty() {
+                break;
+            }
+
+            if trimmed.starts_with(CONTENT_LENGTH_HEADER) {
+                let value = trimmed[CONTENT_LENGTH_HEADER.len()..]
+                    .trim_start_matches(':')
+                    .trim();
+
+                content_length = Some(value.parse::<usize>()
+                    .chain_err(|| "Unable to parse Content-Length header")?);
+            }
+        }
+
+        let content_length = content_length
+            .ok_or_else(|| Error::from("Missing Content-Length header in frame"))?;
+
+        let mut body = vec![0u8; content_length];
+
+        self.reader.read_exact(&mut body)
+            .chain_err(|| "Unable to read frame body")?;
+
+        serde_json::from_slice(&body)
+            .chain_err(|| "Unable to deserialize frame body")
+    }
+}
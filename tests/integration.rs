@@ -0,0 +1,174 @@
+extern crate assert_cmd;
+extern crate serde_json;
+
+use assert_cmd::cargo::CommandCargoExt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How long the test is willing to wait for the mock server to receive a
+/// connection before failing loudly instead of hanging.
+const MOCK_SERVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Starts a mock HTTP server on an ephemeral port that accepts exactly one
+/// connection, replies `200 OK` with an empty body, and hands the request
+/// body back to the caller over a channel so the test can wait for it with
+/// a bound instead of blocking forever if the binary under test never
+/// connects.
+fn spawn_mock_server() -> (String, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to bind mock server");
+    let addr = listener.local_addr().expect("Unable to read mock server address");
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("Mock server did not receive a connection");
+        let mut reader = BufReader::new(stream.try_clone().expect("Unable to clone mock server stream"));
+
+        let mut content_length = 0usize;
+
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).expect("Unable to read request header line");
+
+            let trimmed = line.trim_end_matches(|c| c == '\r' || c == '\n');
+
+            if trimmed.is_empty() {
+                break;
+            }
+
+            if let Some(value) = trimmed.to_lowercase().strip_prefix("content-length:") {
+                content_length = value.trim().parse().expect("Unable to parse Content-Length");
+            }
+        }
+
+        let mut body = vec![0u8; content_length];
+        std::io::Read::read_exact(&mut reader, &mut body).expect("Unable to read request body");
+
+        let mut stream = stream;
+        stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .expect("Unable to write mock server response");
+
+        let body = String::from_utf8(body).expect("Request body was not valid UTF-8");
+        let _ = tx.send(body);
+    });
+
+    (format!("http://{}", addr), rx)
+}
+
+#[test]
+fn posts_expected_json_shape() {
+    let (url, rx) = spawn_mock_server();
+
+    let mut child = Command::cargo_bin("comm_service_rep")
+        .expect("Unable to find binary under test")
+        .args(&["-n", "caller", "-r", ".+", "-c", "echo hi", "-d", &url, "-i", "10"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("Unable to spawn binary under test");
+
+    // Wait for the mock server to actually receive the request before
+    // signaling shutdown: the tick loop waits out its interval before
+    // sending, so an ENTER fired blindly right after spawn can win the
+    // race against the first send and the repeater would exit having
+    // never sent anything.
+    let body = rx.recv_timeout(MOCK_SERVER_TIMEOUT)
+        .expect("Mock server did not receive a request in time");
+
+    child.stdin.take().expect("Child stdin was not piped")
+        .write_all(b"\n")
+        .expect("Unable to write to child stdin");
+
+    let status = child.wait().expect("Unable to wait on child process");
+    assert!(status.success());
+
+    let json: serde_json::Value = serde_json::from_str(&body).expect("Posted body was not JSON");
+
+    assert_eq!(json["id"], "caller");
+    assert_eq!(json["cmdIdRe"], ".+");
+    assert_eq!(json["cmd"], "echo hi");
+}
+
+#[test]
+fn dry_run_prints_request_without_sending() {
+    let mut child = Command::cargo_bin("comm_service_rep")
+        .expect("Unable to find binary under test")
+        .args(&["-n", "caller", "-r", ".+", "-c", "echo hi", "-d", "http://127.0.0.1:1", "-i", "10", "--dry-run"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Unable to spawn binary under test");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("Child stdout was not piped"));
+    let mut collected = String::new();
+
+    // As above: only signal shutdown once we've actually observed a dry
+    // run line, rather than racing the first tick blindly.
+    loop {
+        let mut line = String::new();
+        let read = stdout.read_line(&mut line).expect("Unable to read child stdout");
+        assert!(read > 0, "Child exited before printing a dry run");
+        collected.push_str(&line);
+
+        if line.contains("Dry run:") {
+            break;
+        }
+    }
+
+    child.stdin.take().expect("Child stdin was not piped")
+        .write_all(b"\n")
+        .expect("Unable to write to child stdin");
+
+    stdout.read_to_string(&mut collected).ok();
+
+    let status = child.wait().expect("Unable to wait on child process");
+    assert!(status.success());
+
+    assert!(collected.contains("Dry run:"));
+    assert!(collected.contains("\"id\":\"caller\""));
+    assert!(collected.contains("\"cmdIdRe\":\".+\""));
+    assert!(collected.contains("\"cmd\":\"echo hi\""));
+}
+
+/// Proves SIGTERM actually triggers graceful shutdown: process supervisors
+/// stop services with SIGTERM, not SIGINT, and `ctrlc` only installs a
+/// SIGTERM handler when built with its `termination` feature. This binary
+/// now handles SIGTERM directly, bypassing that feature gate.
+#[cfg(unix)]
+#[test]
+fn sigterm_triggers_graceful_shutdown() {
+    let mut child = Command::cargo_bin("comm_service_rep")
+        .expect("Unable to find binary under test")
+        .args(&["-n", "caller", "-r", ".+", "-c", "echo hi", "-d", "http://127.0.0.1:1", "-i", "10", "--dry-run"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Unable to spawn binary under test");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("Child stdout was not piped"));
+
+    // Wait until the signal handlers are actually installed before sending
+    // SIGTERM, rather than racing it against process startup.
+    loop {
+        let mut line = String::new();
+        let read = stdout.read_line(&mut line).expect("Unable to read child stdout");
+        assert!(read > 0, "Child exited before signal handlers were installed");
+
+        if line.contains("Press [ENTER] to terminate") {
+            break;
+        }
+    }
+
+    let status = Command::new("kill")
+        .args(&["-TERM", &child.id().to_string()])
+        .status()
+        .expect("Unable to run kill -TERM");
+
+    assert!(status.success(), "kill -TERM did not run successfully");
+
+    let status = child.wait().expect("Unable to wait on child process");
+    assert!(status.success(), "Process did not shut down gracefully after SIGTERM");
+}